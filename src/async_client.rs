@@ -0,0 +1,180 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::frame;
+use crate::{parse_state, MagicHomeActionError, MagicHomeState, TransitionType};
+
+async fn get_state(stream: &mut TcpStream) -> std::io::Result<[u8; 14]> {
+    stream.write_all(&frame::query_state_frame()).await?;
+
+    let mut feedback_buf: [u8; 14] = [0; 14];
+    stream.read_exact(&mut feedback_buf).await?;
+
+    Ok(feedback_buf)
+}
+
+/// Async counterpart to [`MagicHome`](crate::MagicHome), driving the same
+/// wire protocol over a `tokio::net::TcpStream` so callers don't have to
+/// spawn a blocking task per device.
+pub struct AsyncMagicHome {
+    stream: Option<TcpStream>,
+}
+
+impl Default for AsyncMagicHome {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncMagicHome {
+    pub fn new() -> Self {
+        Self { stream: None }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub async fn connect(&mut self, addr: &str) -> std::io::Result<()> {
+        let mut stream = TcpStream::connect(addr).await?;
+        let _ = get_state(&mut stream).await?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    pub async fn state(&mut self) -> Result<MagicHomeState, MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        let state = get_state(stream)
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        parse_state(state)
+    }
+
+    pub async fn set_color(&mut self, rgb: [u8; 3]) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_color_frame(rgb))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn set_warm_white(&mut self, warm_white: u8) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_channels_frame(
+                [0, 0, 0],
+                warm_white,
+                0,
+                frame::MASK_WHITES_ONLY,
+            ))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn set_cool_white(&mut self, cool_white: u8) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_channels_frame(
+                [0, 0, 0],
+                0,
+                cool_white,
+                frame::MASK_WHITES_ONLY,
+            ))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn set_rgbww(&mut self, channels: [u8; 5]) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        let rgb = [channels[0], channels[1], channels[2]];
+        stream
+            .write_all(&frame::set_channels_frame(
+                rgb,
+                channels[3],
+                channels[4],
+                frame::MASK_ALL,
+            ))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn power(&mut self, value: bool) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_power_frame(value))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn set_preset(&mut self, pattern: u8, speed: u8) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_preset_frame(pattern, speed))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+
+    pub async fn set_custom_pattern(
+        &mut self,
+        colors: &[[u8; 3]],
+        transition: TransitionType,
+        speed: u8,
+    ) -> Result<(), MagicHomeActionError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(MagicHomeActionError::NotConnected)?;
+
+        stream
+            .write_all(&frame::set_custom_pattern_frame(
+                colors,
+                transition.to_byte(),
+                speed,
+            ))
+            .await
+            .map_err(MagicHomeActionError::IoError)?;
+
+        Ok(())
+    }
+}
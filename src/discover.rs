@@ -0,0 +1,129 @@
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::MagicHomeActionError;
+
+const DISCOVERY_PORT: u16 = 48899;
+const DISCOVERY_PAYLOAD: &[u8] = b"HF-A11ASSISTHREAD";
+
+/// A controller found on the LAN in response to a [`discover`] broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    pub ip: String,
+    pub mac: String,
+    pub model: String,
+}
+
+/// Broadcasts the LEDENET discovery payload on the local network and
+/// collects replies for `timeout`, returning one [`DiscoveredDevice`] per
+/// unique MAC address. Feed `device.ip` into [`MagicHome::connect`](crate::MagicHome::connect).
+pub fn discover(timeout: Duration) -> Result<Vec<DiscoveredDevice>, MagicHomeActionError> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(MagicHomeActionError::IoError)?;
+    socket
+        .set_broadcast(true)
+        .map_err(MagicHomeActionError::IoError)?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .map_err(MagicHomeActionError::IoError)?;
+    socket
+        .send_to(
+            DISCOVERY_PAYLOAD,
+            ("255.255.255.255", DISCOVERY_PORT),
+        )
+        .map_err(MagicHomeActionError::IoError)?;
+
+    let mut devices: Vec<DiscoveredDevice> = vec![];
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; 256];
+
+    while Instant::now() < deadline {
+        match socket.recv_from(&mut buf) {
+            Ok((len, _)) => {
+                if let Some(device) = parse_reply(&buf[..len]) {
+                    push_unique(&mut devices, device);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                continue;
+            }
+            Err(e) => return Err(MagicHomeActionError::IoError(e)),
+        }
+    }
+
+    Ok(devices)
+}
+
+fn parse_reply(buf: &[u8]) -> Option<DiscoveredDevice> {
+    let text = std::str::from_utf8(buf).ok()?;
+    let mut parts = text.trim().splitn(3, ',');
+    let ip = parts.next()?.to_string();
+    let mac = parts.next()?.to_string();
+    let model = parts.next()?.to_string();
+    Some(DiscoveredDevice { ip, mac, model })
+}
+
+fn push_unique(devices: &mut Vec<DiscoveredDevice>, device: DiscoveredDevice) {
+    if !devices.iter().any(|d| d.mac == device.mac) {
+        devices.push(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_well_formed_reply() {
+        let device = parse_reply(b"192.168.1.50,ACCF2394,AK001-ZJ2145").unwrap();
+        assert_eq!(device.ip, "192.168.1.50");
+        assert_eq!(device.mac, "ACCF2394");
+        assert_eq!(device.model, "AK001-ZJ2145");
+    }
+
+    #[test]
+    fn rejects_replies_missing_fields() {
+        assert!(parse_reply(b"").is_none());
+        assert!(parse_reply(b"192.168.1.50").is_none());
+        assert!(parse_reply(b"192.168.1.50,ACCF2394").is_none());
+    }
+
+    #[test]
+    fn rejects_non_utf8_replies() {
+        assert!(parse_reply(&[0xFF, 0xFE, 0xFD]).is_none());
+    }
+
+    #[test]
+    fn push_unique_dedups_by_mac() {
+        let mut devices = vec![];
+        push_unique(
+            &mut devices,
+            DiscoveredDevice {
+                ip: "192.168.1.50".to_string(),
+                mac: "ACCF2394".to_string(),
+                model: "AK001-ZJ2145".to_string(),
+            },
+        );
+        // Same MAC, different IP/model: still deduped.
+        push_unique(
+            &mut devices,
+            DiscoveredDevice {
+                ip: "192.168.1.51".to_string(),
+                mac: "ACCF2394".to_string(),
+                model: "AK001-ZJ2146".to_string(),
+            },
+        );
+        push_unique(
+            &mut devices,
+            DiscoveredDevice {
+                ip: "192.168.1.52".to_string(),
+                mac: "DIFFERENT".to_string(),
+                model: "AK001-ZJ2145".to_string(),
+            },
+        );
+
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].ip, "192.168.1.50");
+        assert_eq!(devices[1].mac, "DIFFERENT");
+    }
+}
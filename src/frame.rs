@@ -0,0 +1,156 @@
+// Shared wire-protocol framing used by both the blocking (`MagicHome`) and
+// async (`AsyncMagicHome`) clients, so the two front ends can't drift apart
+// on the byte layout documented in `lib.rs`.
+
+pub(crate) fn get_checksum(buf: &[u8]) -> u8 {
+    buf.iter().fold(0u64, |a, b| a + (*b as u64)) as u8
+}
+
+pub(crate) fn query_state_frame() -> Vec<u8> {
+    let mut buf = vec![0x81, 0x8A, 0x8B];
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+// Matches the color-mode byte documented at the top of `lib.rs` for the
+// *read* path (f0 colors, 0f whites, 00 all); the write frame reuses the
+// same three values as its channel mask.
+pub(crate) const MASK_RGB_ONLY: u8 = 0xF0;
+pub(crate) const MASK_WHITES_ONLY: u8 = 0x0F;
+pub(crate) const MASK_ALL: u8 = 0x00;
+
+// Narrower than `set_channels_frame`: carries only RGB, no ww/cw bytes.
+pub(crate) fn set_color_frame(rgb: [u8; 3]) -> Vec<u8> {
+    let mut buf = vec![0x31];
+    buf.extend_from_slice(&rgb);
+    buf.push(0x00);
+    buf.push(MASK_RGB_ONLY);
+    buf.push(0x0F);
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+pub(crate) fn set_power_frame(value: bool) -> Vec<u8> {
+    let power_byte = if value { 0x23 } else { 0x24 };
+    let mut buf = vec![0x71, power_byte, 0x0F];
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+pub(crate) fn set_channels_frame(rgb: [u8; 3], ww: u8, cw: u8, mask: u8) -> Vec<u8> {
+    let mut buf = vec![0x31];
+    buf.extend_from_slice(&rgb);
+    buf.push(ww);
+    buf.push(cw);
+    buf.push(mask);
+    buf.push(0x0F);
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+pub(crate) fn header_valid(state: &[u8; 14]) -> bool {
+    state[0] == 0x81 && get_checksum(&state[..13]) == state[13]
+}
+
+pub(crate) fn set_preset_frame(pattern: u8, speed: u8) -> Vec<u8> {
+    let mut buf = vec![0x61, pattern, speed, 0x0F];
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+const CUSTOM_PATTERN_MAX_COLORS: usize = 16;
+
+pub(crate) fn set_custom_pattern_frame(
+    colors: &[[u8; 3]],
+    transition_byte: u8,
+    speed: u8,
+) -> Vec<u8> {
+    let mut buf = vec![0x51];
+    for slot in 0..CUSTOM_PATTERN_MAX_COLORS {
+        match colors.iter().take(CUSTOM_PATTERN_MAX_COLORS).nth(slot) {
+            Some(rgb) => buf.extend_from_slice(rgb),
+            None => buf.extend_from_slice(&[0x01, 0x02, 0x03]),
+        }
+    }
+    buf.push(speed);
+    buf.push(transition_byte);
+    buf.push(0xFF);
+    buf.push(0x0F);
+    buf.push(get_checksum(&buf));
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_masks_are_distinct_with_a_fixed_trailer() {
+        let whites_only = set_channels_frame([0, 0, 0], 0x80, 0x10, MASK_WHITES_ONLY);
+        let all = set_channels_frame([1, 2, 3], 0x80, 0x10, MASK_ALL);
+
+        assert_eq!(whites_only[6], MASK_WHITES_ONLY);
+        assert_eq!(all[6], MASK_ALL);
+        assert_ne!(whites_only[6], all[6]);
+        // trailer byte is fixed regardless of mask
+        assert_eq!(whites_only[7], 0x0F);
+        assert_eq!(all[7], 0x0F);
+    }
+
+    #[test]
+    fn set_channels_frame_checksum_is_sum_of_preceding_bytes() {
+        let buf = set_channels_frame([10, 20, 30], 40, 50, MASK_ALL);
+        assert_eq!(buf.len(), 9);
+        assert_eq!(*buf.last().unwrap(), get_checksum(&buf[..8]));
+    }
+
+    #[test]
+    fn set_color_frame_uses_rgb_only_mask_and_fixed_trailer() {
+        let buf = set_color_frame([5, 6, 7]);
+        assert_eq!(buf, vec![0x31, 5, 6, 7, 0x00, MASK_RGB_ONLY, 0x0F, get_checksum(&[0x31, 5, 6, 7, 0x00, MASK_RGB_ONLY, 0x0F])]);
+    }
+
+    #[test]
+    fn set_preset_frame_layout_and_checksum() {
+        let buf = set_preset_frame(0x25, 0x64);
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf[..4], [0x61, 0x25, 0x64, 0x0F]);
+        assert_eq!(*buf.last().unwrap(), get_checksum(&buf[..4]));
+    }
+
+    #[test]
+    fn set_custom_pattern_frame_pads_unused_slots() {
+        let colors = [[1, 1, 1], [2, 2, 2]];
+        let buf = set_custom_pattern_frame(&colors, 0x3A, 0x64);
+
+        assert_eq!(buf[0], 0x51);
+        assert_eq!(&buf[1..4], &[1, 1, 1]);
+        assert_eq!(&buf[4..7], &[2, 2, 2]);
+        for slot in 2..CUSTOM_PATTERN_MAX_COLORS {
+            let offset = 1 + slot * 3;
+            assert_eq!(&buf[offset..offset + 3], &[0x01, 0x02, 0x03]);
+        }
+
+        let trailer_offset = 1 + CUSTOM_PATTERN_MAX_COLORS * 3;
+        assert_eq!(buf[trailer_offset], 0x64); // speed
+        assert_eq!(buf[trailer_offset + 1], 0x3A); // transition_byte
+        assert_eq!(buf[trailer_offset + 2], 0xFF);
+        assert_eq!(buf[trailer_offset + 3], 0x0F);
+        assert_eq!(buf.len(), trailer_offset + 5);
+        assert_eq!(*buf.last().unwrap(), get_checksum(&buf[..buf.len() - 1]));
+    }
+
+    #[test]
+    fn set_custom_pattern_frame_truncates_beyond_16_colors() {
+        let colors: Vec<[u8; 3]> = (0..20).map(|i| [i as u8; 3]).collect();
+        let buf = set_custom_pattern_frame(&colors, 0x3A, 0x64);
+
+        for slot in 0..CUSTOM_PATTERN_MAX_COLORS {
+            let offset = 1 + slot * 3;
+            assert_eq!(&buf[offset..offset + 3], &[slot as u8; 3]);
+        }
+        // Colors 16..20 are dropped entirely: the frame is the same length
+        // as it would be for exactly 16 colors.
+        assert_eq!(buf.len(), 1 + CUSTOM_PATTERN_MAX_COLORS * 3 + 5);
+    }
+}
@@ -1,5 +1,17 @@
-use std::io::{Read, Write, Error};
+use std::io::{Read, Write, Error, ErrorKind};
 use std::net::TcpStream;
+use std::time::Duration;
+
+mod frame;
+mod async_client;
+mod discover;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+
+pub use async_client::AsyncMagicHome;
+pub use discover::{discover, DiscoveredDevice};
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttBridge, MqttBridgeError};
 
 // response from a 5-channel LEDENET controller:
 // pos  0  1  2  3  4  5  6  7  8  9 10 11 12 13
@@ -21,45 +33,246 @@ use std::net::TcpStream;
 //     msg head
 //
 
-fn get_checksum(buf: &[u8]) -> u8 {
-    buf.iter().fold(0u64, |a, b| a + (*b as u64)) as u8
-}
-
 fn get_state(mut stream: impl Read + Write) -> Result<[u8; 14], Error> {
-    let mut query_buf: Vec<u8> = vec![];
-    query_buf.push(0x81);
-    query_buf.push(0x8A);
-    query_buf.push(0x8B);
-    query_buf.push(get_checksum(&query_buf));
-    stream.write(&query_buf)?;
+    stream.write_all(&frame::query_state_frame())?;
 
     let mut feedback_buf: [u8; 14] = [0; 14];
-    stream.read(&mut feedback_buf)?;
+    stream.read_exact(&mut feedback_buf)?;
 
     Ok(feedback_buf)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    WW,
+    WWCW,
+    RGB,
+    RGBW,
+    RGBWW,
+}
+
+impl Mode {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x01 => Some(Mode::WW),
+            0x02 => Some(Mode::WWCW),
+            0x03 => Some(Mode::RGB),
+            0x04 => Some(Mode::RGBW),
+            0x05 => Some(Mode::RGBWW),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MagicHomeState {
     pub is_enabled: bool,
+    pub mode: Mode,
+    pub preset_pattern: u8,
+    pub speed: u8,
     pub red: u8,
     pub green: u8,
     pub blue: u8,
+    pub warm_white: u8,
+    pub version: u8,
+    pub cool_white: u8,
 }
 
 #[derive(Debug)]
 pub enum MagicHomeActionError {
     NotConnected,
     IoError(Error),
+    InvalidResponse,
+}
+
+/// How `set_custom_pattern` transitions between colors in the cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionType {
+    Gradual,
+    Jumping,
+    Strobe,
+}
+
+impl TransitionType {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            TransitionType::Gradual => 0x3A,
+            TransitionType::Jumping => 0x3B,
+            TransitionType::Strobe => 0x3C,
+        }
+    }
+}
+
+pub(crate) fn parse_state(state: [u8; 14]) -> Result<MagicHomeState, MagicHomeActionError> {
+    if !frame::header_valid(&state) {
+        return Err(MagicHomeActionError::InvalidResponse);
+    }
+    let mode = Mode::from_byte(state[3]).ok_or(MagicHomeActionError::InvalidResponse)?;
+
+    Ok(MagicHomeState {
+        is_enabled: state[2] != 0x24,
+        mode,
+        preset_pattern: state[4],
+        speed: state[5],
+        red: state[6],
+        green: state[7],
+        blue: state[8],
+        warm_white: state[9],
+        version: state[10],
+        cool_white: state[11],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_state(mode_byte: u8) -> [u8; 14] {
+        let mut state = [
+            0x81, 0x25, 0x23, mode_byte, 0x26, 0x05, 0x11, 0x22, 0x33, 0x44, 0x01, 0x55, 0x00,
+            0x00,
+        ];
+        state[13] = frame::get_checksum(&state[..13]);
+        state
+    }
+
+    #[test]
+    fn parse_state_reads_every_documented_field() {
+        let state = parse_state(valid_state(0x05)).unwrap();
+        assert!(state.is_enabled);
+        assert_eq!(state.mode, Mode::RGBWW);
+        assert_eq!(state.preset_pattern, 0x26);
+        assert_eq!(state.speed, 0x05);
+        assert_eq!(state.red, 0x11);
+        assert_eq!(state.green, 0x22);
+        assert_eq!(state.blue, 0x33);
+        assert_eq!(state.warm_white, 0x44);
+        assert_eq!(state.version, 0x01);
+        assert_eq!(state.cool_white, 0x55);
+    }
+
+    #[test]
+    fn parse_state_rejects_bad_header_byte() {
+        let mut state = valid_state(0x03);
+        state[0] = 0x80;
+        assert!(matches!(
+            parse_state(state),
+            Err(MagicHomeActionError::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn parse_state_rejects_bad_checksum() {
+        let mut state = valid_state(0x03);
+        state[13] ^= 0xFF;
+        assert!(matches!(
+            parse_state(state),
+            Err(MagicHomeActionError::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn parse_state_rejects_unknown_mode_byte() {
+        assert!(matches!(
+            parse_state(valid_state(0x00)),
+            Err(MagicHomeActionError::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn setter_called_while_disconnected_does_not_leak_into_a_later_send() {
+        use std::net::TcpListener;
+        use std::sync::mpsc;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut query = [0u8; 4];
+            stream.read_exact(&mut query).unwrap();
+            stream.write_all(&[0u8; 14]).unwrap();
+
+            let mut buf = [0u8; 64];
+            if let Ok(n) = stream.read(&mut buf) {
+                let _ = tx.send(buf[..n].to_vec());
+            }
+        });
+
+        let mut device = MagicHome::new();
+        assert!(matches!(
+            device.set_color([9, 9, 9]),
+            Err(MagicHomeActionError::NotConnected)
+        ));
+
+        device.connect(&addr.to_string()).unwrap();
+        device.power(true).unwrap();
+
+        let received = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(received[0], 0x71, "rejected set_color frame leaked into the queue");
+    }
+}
+
+/// Controls how [`MagicHome`] recovers from a dropped connection: how many
+/// times it re-dials the remembered address, waiting `backoff * attempt`
+/// between tries, before giving up and surfacing the error.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+fn is_recoverable(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionReset | ErrorKind::BrokenPipe | ErrorKind::WouldBlock
+    )
 }
 
 pub struct MagicHome {
     stream: Option<TcpStream>,
+    addr: Option<String>,
+    retry_policy: RetryPolicy,
+    pending: Vec<Vec<u8>>,
+}
+
+impl Default for MagicHome {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MagicHome {
     pub fn new() -> Self {
-        Self { stream: None }
+        Self {
+            stream: None,
+            addr: None,
+            retry_policy: RetryPolicy::default(),
+            pending: vec![],
+        }
+    }
+
+    /// Like [`MagicHome::new`], but reconnects with `policy` instead of the
+    /// default retry policy whenever a write fails with a recoverable I/O
+    /// error (`ConnectionReset`/`BrokenPipe`/`WouldBlock`).
+    pub fn with_retry_policy(policy: RetryPolicy) -> Self {
+        Self {
+            stream: None,
+            addr: None,
+            retry_policy: policy,
+            pending: vec![],
+        }
     }
 
     pub fn is_connected(&self) -> bool {
@@ -70,60 +283,152 @@ impl MagicHome {
         let stream = TcpStream::connect(addr)?;
         let _ = get_state(&stream)?;
         self.stream = Some(stream);
+        self.addr = Some(addr.to_string());
         Ok(())
     }
 
-    pub fn state(&mut self) -> Result<MagicHomeState, MagicHomeActionError> {
-        let stream = self
-            .stream
-            .as_ref()
+    fn reconnect(&mut self) -> Result<(), MagicHomeActionError> {
+        let addr = self
+            .addr
+            .clone()
             .ok_or(MagicHomeActionError::NotConnected)?;
 
-        let state = get_state(stream).map_err(|e| MagicHomeActionError::IoError(e))?;
-
-        Ok(MagicHomeState {
-            is_enabled: state[2] != 0x24,
-            red: state[6],
-            green: state[7],
-            blue: state[8],
-        })
+        let mut attempt = 0;
+        loop {
+            match self.connect(&addr) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    attempt += 1;
+                    if attempt >= self.retry_policy.max_retries {
+                        return Err(MagicHomeActionError::IoError(e));
+                    }
+                    std::thread::sleep(self.retry_policy.backoff * attempt);
+                }
+            }
+        }
     }
 
-    pub fn set_color(&mut self, rgb: [u8; 3]) -> Result<(), MagicHomeActionError> {
-        let mut stream = self
-            .stream
-            .as_ref()
-            .ok_or(MagicHomeActionError::NotConnected)?;
+    // Queues `frame`, then flushes the queue in order. A write that fails
+    // with a recoverable error triggers a reconnect (with retries/backoff)
+    // before being retried; anything still queued after that point is
+    // flushed on the next call instead of being dropped. A frame that fails
+    // for good (non-recoverable I/O error, a reconnect that fails, or a
+    // recoverable error that persists after `max_retries` reconnect cycles)
+    // is dropped from the head of the queue so later commands can still
+    // make progress.
+    fn send(&mut self, frame: Vec<u8>) -> Result<(), MagicHomeActionError> {
+        self.pending.push(frame);
+        let mut reconnect_cycles = 0;
 
-        let mut buf = vec![];
-        buf.push(0x31);
-        for i in 0..rgb.len() {
-            buf.push(rgb[i]);
+        while let Some(frame) = self.pending.first().cloned() {
+            let mut stream = match self.stream.as_ref() {
+                Some(stream) => stream,
+                None => {
+                    self.pending.remove(0);
+                    return Err(MagicHomeActionError::NotConnected);
+                }
+            };
+
+            match stream.write_all(&frame) {
+                Ok(()) => {
+                    self.pending.remove(0);
+                }
+                Err(e) if is_recoverable(&e) => {
+                    reconnect_cycles += 1;
+                    if reconnect_cycles > self.retry_policy.max_retries {
+                        self.pending.remove(0);
+                        return Err(MagicHomeActionError::IoError(e));
+                    }
+                    if let Err(e) = self.reconnect() {
+                        self.pending.remove(0);
+                        return Err(e);
+                    }
+                }
+                Err(e) => {
+                    self.pending.remove(0);
+                    return Err(MagicHomeActionError::IoError(e));
+                }
+            }
         }
-        buf.push(0x00);
-        buf.push(0xF0);
-        buf.push(0x0F);
-        buf.push(get_checksum(&buf));
-        stream
-            .write(&buf)
-            .map_err(|e| MagicHomeActionError::IoError(e))?;
 
         Ok(())
     }
 
+    // Like `send`, a read that fails with a recoverable error reconnects
+    // (with retries/backoff) and is retried, up to `max_retries` cycles.
+    pub fn state(&mut self) -> Result<MagicHomeState, MagicHomeActionError> {
+        let mut reconnect_cycles = 0;
+
+        loop {
+            let stream = self
+                .stream
+                .as_ref()
+                .ok_or(MagicHomeActionError::NotConnected)?;
+
+            match get_state(stream) {
+                Ok(state) => return parse_state(state),
+                Err(e) if is_recoverable(&e) => {
+                    reconnect_cycles += 1;
+                    if reconnect_cycles > self.retry_policy.max_retries {
+                        return Err(MagicHomeActionError::IoError(e));
+                    }
+                    self.reconnect()?;
+                }
+                Err(e) => return Err(MagicHomeActionError::IoError(e)),
+            }
+        }
+    }
+
+    pub fn set_color(&mut self, rgb: [u8; 3]) -> Result<(), MagicHomeActionError> {
+        self.send(frame::set_color_frame(rgb))
+    }
+
+    pub fn set_warm_white(&mut self, warm_white: u8) -> Result<(), MagicHomeActionError> {
+        self.send(frame::set_channels_frame(
+            [0, 0, 0],
+            warm_white,
+            0,
+            frame::MASK_WHITES_ONLY,
+        ))
+    }
+
+    pub fn set_cool_white(&mut self, cool_white: u8) -> Result<(), MagicHomeActionError> {
+        self.send(frame::set_channels_frame(
+            [0, 0, 0],
+            0,
+            cool_white,
+            frame::MASK_WHITES_ONLY,
+        ))
+    }
+
+    pub fn set_rgbww(&mut self, channels: [u8; 5]) -> Result<(), MagicHomeActionError> {
+        let rgb = [channels[0], channels[1], channels[2]];
+        self.send(frame::set_channels_frame(
+            rgb,
+            channels[3],
+            channels[4],
+            frame::MASK_ALL,
+        ))
+    }
+
     pub fn power(&mut self, value: bool) -> Result<(), MagicHomeActionError> {
-        let mut stream = self
-            .stream
-            .as_ref()
-            .ok_or(MagicHomeActionError::NotConnected)?;
+        self.send(frame::set_power_frame(value))
+    }
 
-        let power_byte = if value { 0x23 } else { 0x24 };
-        let mut buf = vec![0x71, power_byte, 0x0F];
-        buf.push(get_checksum(&buf));
-        stream
-            .write(&buf)
-            .map_err(|e| MagicHomeActionError::IoError(e))?;
+    pub fn set_preset(&mut self, pattern: u8, speed: u8) -> Result<(), MagicHomeActionError> {
+        self.send(frame::set_preset_frame(pattern, speed))
+    }
 
-        Ok(())
+    pub fn set_custom_pattern(
+        &mut self,
+        colors: &[[u8; 3]],
+        transition: TransitionType,
+        speed: u8,
+    ) -> Result<(), MagicHomeActionError> {
+        self.send(frame::set_custom_pattern_frame(
+            colors,
+            transition.to_byte(),
+            speed,
+        ))
     }
 }
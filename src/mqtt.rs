@@ -0,0 +1,171 @@
+//! Home Assistant integration: bridges a [`MagicHome`] to an MQTT broker and
+//! exposes it as an MQTT light via HA's discovery protocol. Enabled with the
+//! `mqtt` feature.
+
+use std::time::Duration;
+
+use rumqttc::{Client, Event, MqttOptions, Packet, Publish, QoS};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::MagicHome;
+
+#[derive(Debug, Deserialize)]
+struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct Command {
+    state: String,
+    color: Option<Color>,
+}
+
+#[derive(Debug)]
+pub enum MqttBridgeError {
+    Mqtt(rumqttc::ClientError),
+    Device(std::io::Error),
+}
+
+impl From<rumqttc::ClientError> for MqttBridgeError {
+    fn from(e: rumqttc::ClientError) -> Self {
+        MqttBridgeError::Mqtt(e)
+    }
+}
+
+impl From<std::io::Error> for MqttBridgeError {
+    fn from(e: std::io::Error) -> Self {
+        MqttBridgeError::Device(e)
+    }
+}
+
+/// Bridges a [`MagicHome`] to an MQTT broker, publishing Home Assistant
+/// discovery config and translating commands on `command_topic` into
+/// `power`/`set_color` calls. Owns the device and transparently re-dials a
+/// dropped TCP link via [`MagicHome::connect`].
+pub struct MqttBridge {
+    device: MagicHome,
+    addr: String,
+    unique_id: String,
+    command_topic: String,
+    state_topic: String,
+    poll_interval: Duration,
+}
+
+impl MqttBridge {
+    pub fn new(device: MagicHome, addr: &str, unique_id: &str) -> Self {
+        Self {
+            device,
+            addr: addr.to_string(),
+            unique_id: unique_id.to_string(),
+            command_topic: format!("magic-home/{}/set", unique_id),
+            state_topic: format!("magic-home/{}/state", unique_id),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+
+    fn discovery_topic(&self) -> String {
+        format!(
+            "homeassistant/light/{}/config",
+            self.unique_id
+        )
+    }
+
+    fn discovery_payload(&self) -> String {
+        json!({
+            "name": self.unique_id,
+            "unique_id": self.unique_id,
+            "schema": "json",
+            "command_topic": self.command_topic,
+            "state_topic": self.state_topic,
+            "rgb": true,
+        })
+        .to_string()
+    }
+
+    /// Connects to the device and the broker, publishes the discovery
+    /// config, then loops forever forwarding commands and polling state.
+    /// Runs until the process is killed or `run` returns an `Err`.
+    pub fn run(&mut self, broker_host: &str, broker_port: u16) -> Result<(), MqttBridgeError> {
+        self.device.connect(&self.addr)?;
+
+        let mut mqtt_options = MqttOptions::new(self.unique_id.clone(), broker_host, broker_port);
+        mqtt_options.set_keep_alive(Duration::from_secs(30));
+
+        let (client, mut connection) = Client::new(mqtt_options, 10);
+        client.publish(
+            self.discovery_topic(),
+            QoS::AtLeastOnce,
+            true,
+            self.discovery_payload(),
+        )?;
+        client.subscribe(&self.command_topic, QoS::AtLeastOnce)?;
+
+        self.publish_state(&client)?;
+        let mut last_poll = std::time::Instant::now();
+
+        for notification in connection.iter() {
+            match notification {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    self.handle_command(&publish)?;
+                    self.publish_state(&client)?;
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    // Dropped broker connection: rumqttc's `Client`/`Connection`
+                    // re-dial internally, so there's nothing to do here but
+                    // keep polling. The device link is handled separately by
+                    // `publish_state`/`handle_command`.
+                }
+            }
+
+            if last_poll.elapsed() >= self.poll_interval {
+                self.publish_state(&client)?;
+                last_poll = std::time::Instant::now();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_command(&mut self, publish: &Publish) -> Result<(), MqttBridgeError> {
+        if publish.topic != self.command_topic {
+            return Ok(());
+        }
+        let Ok(command) = serde_json::from_slice::<Command>(&publish.payload) else {
+            return Ok(());
+        };
+
+        if self.device.power(command.state == "ON").is_err() {
+            let _ = self.device.connect(&self.addr);
+        }
+        if let Some(color) = command.color {
+            let _ = self.device.set_color([color.r, color.g, color.b]);
+        }
+
+        Ok(())
+    }
+
+    fn publish_state(&mut self, client: &Client) -> Result<(), MqttBridgeError> {
+        let state = match self.device.state() {
+            Ok(state) => state,
+            Err(_) => {
+                // Dropped device link: reconnect and try again on the next poll.
+                let _ = self.device.connect(&self.addr);
+                return Ok(());
+            }
+        };
+
+        let payload = json!({
+            "state": if state.is_enabled { "ON" } else { "OFF" },
+            "color": { "r": state.red, "g": state.green, "b": state.blue },
+        })
+        .to_string();
+
+        client
+            .publish(&self.state_topic, QoS::AtLeastOnce, false, payload)
+            .map_err(MqttBridgeError::from)
+    }
+}